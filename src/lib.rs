@@ -1,41 +1,401 @@
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::hash::{Hash, Hasher};
 use std::str::FromStr;
 
-use generic_array::typenum::{U32, U8};
-use generic_array::{ArrayLength, GenericArray};
+use generic_array::GenericArray;
 use hex::FromHexError;
-use sha2::{Digest, Sha256};
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use sha2::{Sha256, Sha512};
+
+
+/// A digest produced by hashing algorithm `A`.
+///
+/// `A` is any type implementing [`digest::Digest`], so `Digest<Sha256>`,
+/// `Digest<Sha512>`, `Digest<Sha3_256>`, etc. are all valid. The output
+/// buffer is sized by `A::OutputSize`, so there's no fixed-width assumption
+/// baked into the type.
+pub struct Digest<A: digest::Digest>(GenericArray<u8, <A as digest::Digest>::OutputSize>);
+
+/// A SHA-256 digest, kept as a convenient alias for the most common case.
+pub type DigestSha256 = Digest<Sha256>;
+
+/// Maps a hashing algorithm to the tokens used to name it in the wild:
+/// the OCI-style `algorithm:hexvalue` digest form (`"sha256"`) and the
+/// HTTP `Digest` header form (`"SHA-256"`).
+pub trait AlgorithmName {
+    const NAME: &'static str;
+    const HTTP_NAME: &'static str;
+}
+
+impl AlgorithmName for Sha256 {
+    const NAME: &'static str = "sha256";
+    const HTTP_NAME: &'static str = "SHA-256";
+}
 
+impl AlgorithmName for Sha512 {
+    const NAME: &'static str = "sha512";
+    const HTTP_NAME: &'static str = "SHA-512";
+}
 
+/// An error parsing a [`Digest`] from its string form.
 #[derive(Debug)]
-pub struct DigestSha256(GenericArray<u8, U32>);
+pub enum ParseDigestError {
+    /// The `algorithm` portion of an `algorithm:hex` digest string didn't
+    /// match the algorithm this `Digest<A>` is parameterized over.
+    UnknownAlgorithm(String),
+    Hex(FromHexError),
+}
+
+impl fmt::Display for ParseDigestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseDigestError::UnknownAlgorithm(algorithm) => {
+                write!(f, "unsupported digest algorithm: {}", algorithm)
+            }
+            ParseDigestError::Hex(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for ParseDigestError {}
+
+impl From<FromHexError> for ParseDigestError {
+    fn from(e: FromHexError) -> Self {
+        ParseDigestError::Hex(e)
+    }
+}
 
-impl DigestSha256 {
-    pub fn from_digestable<'a, A, I>(val: &'a A) -> DigestSha256
+impl<A: digest::Digest> Digest<A> {
+    pub fn from_digestable<'a, T, I>(val: &'a T) -> Digest<A>
     where
-        A: Digestable<'a, I>,
-        I: Iterator<Item = &'a [u8]>,
+        T: Digestable<'a, I>,
+        I: Iterator<Item = Cow<'a, [u8]>>,
     {
-        let mut hasher = Sha256::new();
+        let mut hasher = A::new();
         for v in val.digestable() {
             hasher.update(v);
         }
         let res = hasher.finalize();
-        DigestSha256(res)
+        Digest(res)
+    }
+
+    /// Compute the digest of `val` and compare it against `self` in
+    /// constant time, so the comparison doesn't leak timing information
+    /// about where a mismatch occurred.
+    pub fn verify<'a, T, I>(&self, val: &'a T) -> bool
+    where
+        T: Digestable<'a, I>,
+        I: Iterator<Item = Cow<'a, [u8]>>,
+    {
+        let computed = Digest::<A>::from_digestable(val);
+
+        let mut acc = 0u8;
+        for (a, b) in self.0.iter().zip(computed.0.iter()) {
+            acc |= a ^ b;
+        }
+
+        acc == 0
+    }
+}
+
+impl<A: digest::Digest> std::fmt::Debug for Digest<A> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Digest").field(&self.0).finish()
+    }
+}
+
+impl<A> FromStr for Digest<A>
+where
+    A: digest::Digest + AlgorithmName,
+{
+    type Err = ParseDigestError;
+
+    fn from_str(s: &str) -> Result<Digest<A>, ParseDigestError> {
+        // Accept a bare hex string for backward compatibility, defaulting
+        // to whatever algorithm `A` is.
+        let hex_value = match s.split_once(':') {
+            Some((algorithm, hex_value)) if algorithm == A::NAME => hex_value,
+            Some((algorithm, _)) => {
+                return Err(ParseDigestError::UnknownAlgorithm(algorithm.to_string()))
+            }
+            None => s,
+        };
+
+        let mut decoded: GenericArray<u8, <A as digest::Digest>::OutputSize> = Default::default();
+        hex::decode_to_slice(hex_value, &mut decoded)?;
+
+        Ok(Digest(decoded))
+    }
+}
+
+impl<A> fmt::Display for Digest<A>
+where
+    A: digest::Digest + AlgorithmName,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", A::NAME, hex::encode(&self.0))
+    }
+}
+
+/// An error parsing a [`Digest`] from an HTTP `Digest` header value.
+#[derive(Debug)]
+pub enum ParseHttpDigestError {
+    /// The header value wasn't `algorithm=value`, or named an algorithm
+    /// other than the one this `Digest<A>` is parameterized over.
+    UnknownAlgorithm(String),
+    /// The decoded bytes weren't `A::OutputSize` long.
+    InvalidLength,
+    Base64(base64::DecodeError),
+}
+
+impl fmt::Display for ParseHttpDigestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseHttpDigestError::UnknownAlgorithm(algorithm) => {
+                write!(f, "unsupported digest algorithm: {}", algorithm)
+            }
+            ParseHttpDigestError::InvalidLength => write!(f, "digest has the wrong length"),
+            ParseHttpDigestError::Base64(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for ParseHttpDigestError {}
+
+impl From<base64::DecodeError> for ParseHttpDigestError {
+    fn from(e: base64::DecodeError) -> Self {
+        ParseHttpDigestError::Base64(e)
+    }
+}
+
+impl<A> Digest<A>
+where
+    A: digest::Digest + AlgorithmName,
+{
+    /// Format as an HTTP `Digest` header value, e.g. `SHA-256=<base64>`.
+    pub fn to_http_header(&self) -> String {
+        format!("{}={}", A::HTTP_NAME, base64::encode(&self.0))
+    }
+
+    /// Parse an HTTP `Digest` header value such as `SHA-256=<base64>`,
+    /// case-folding the algorithm token.
+    pub fn from_http_header(s: &str) -> Result<Digest<A>, ParseHttpDigestError> {
+        let (algorithm, value) = s
+            .split_once('=')
+            .ok_or_else(|| ParseHttpDigestError::UnknownAlgorithm(s.to_string()))?;
+
+        if !algorithm.eq_ignore_ascii_case(A::HTTP_NAME) {
+            return Err(ParseHttpDigestError::UnknownAlgorithm(algorithm.to_string()));
+        }
+
+        let decoded_bytes = base64::decode(value)?;
+
+        let mut decoded: GenericArray<u8, <A as digest::Digest>::OutputSize> = Default::default();
+        if decoded_bytes.len() != decoded.len() {
+            return Err(ParseHttpDigestError::InvalidLength);
+        }
+        decoded.copy_from_slice(&decoded_bytes);
+
+        Ok(Digest(decoded))
+    }
+}
+
+impl<A: digest::Digest> fmt::LowerHex for Digest<A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for byte in self.0.iter() {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
     }
 }
 
-impl FromStr for DigestSha256 {
-    type Err = FromHexError;
+impl<A: digest::Digest> PartialEq for Digest<A> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
 
-    fn from_str(s: &str) -> Result<DigestSha256, FromHexError> {
-        let mut decoded: GenericArray<u8, U32> = Default::default();
-        hex::decode_to_slice(s, &mut decoded)?;
+impl<A: digest::Digest> Eq for Digest<A> {}
 
-        Ok(DigestSha256(decoded))
+impl<A: digest::Digest> Hash for Digest<A> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state)
     }
 }
 
-pub trait Digestable<'a, I: Iterator<Item = &'a [u8]>> {
+impl<A: digest::Digest> PartialOrd for Digest<A> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<A: digest::Digest> Ord for Digest<A> {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.0.cmp(&other.0)
+    }
+}
+
+impl<A> Serialize for Digest<A>
+where
+    A: digest::Digest + AlgorithmName,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de, A> Deserialize<'de> for Digest<A>
+where
+    A: digest::Digest + AlgorithmName,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        Digest::from_str(&s).map_err(D::Error::custom)
+    }
+}
+
+/// Leaves and internal nodes are hashed with distinct domain-separation
+/// prefixes, so a leaf hash can never be replayed as an internal node (and
+/// vice versa) to forge a proof.
+const MERKLE_LEAF_PREFIX: [u8; 1] = [0x00];
+const MERKLE_NODE_PREFIX: [u8; 1] = [0x01];
+
+type MerkleHash<A> = GenericArray<u8, <A as digest::Digest>::OutputSize>;
+
+/// A binary Merkle tree over a sequence of [`Digestable`] items, supporting
+/// inclusion proofs without re-hashing the whole sequence.
+///
+/// Built bottom-up: each leaf is `A(0x00 || item_bytes)`, each internal node
+/// is `A(0x01 || left || right)`, and an odd node out at any level is
+/// promoted to the next level unchanged rather than duplicated.
+pub struct MerkleDigest<A: digest::Digest> {
+    levels: Vec<Vec<MerkleHash<A>>>,
+}
+
+/// The sibling hashes (and their left/right position relative to the leaf
+/// being proven) needed to recompute a [`MerkleDigest`]'s root for a single
+/// leaf.
+pub struct MerkleProof<A: digest::Digest> {
+    /// Ordered from the leaf's sibling up to the level below the root.
+    /// `true` means the sibling is the left-hand node.
+    siblings: Vec<(MerkleHash<A>, bool)>,
+}
+
+impl<A: digest::Digest> MerkleDigest<A> {
+    /// Build a Merkle tree over `items`. Returns `None` for an empty slice,
+    /// which has no leaves and so no well-defined root.
+    pub fn new<'a, T, I>(items: &'a [T]) -> Option<MerkleDigest<A>>
+    where
+        T: Digestable<'a, I>,
+        I: Iterator<Item = Cow<'a, [u8]>>,
+    {
+        if items.is_empty() {
+            return None;
+        }
+
+        let leaves: Vec<_> = items.iter().map(Self::leaf_hash).collect();
+        let mut levels = vec![leaves];
+
+        while levels.last().expect("levels is never empty").len() > 1 {
+            let previous = levels.last().expect("levels is never empty");
+            let next = previous
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => Self::node_hash(left, right),
+                    [only] => only.clone(),
+                    _ => unreachable!("chunks(2) never yields more than 2 elements"),
+                })
+                .collect();
+
+            levels.push(next);
+        }
+
+        Some(MerkleDigest { levels })
+    }
+
+    /// The Merkle root, as an ordinary [`Digest`].
+    pub fn root(&self) -> Digest<A> {
+        let root = self.levels.last().expect("levels is never empty")[0].clone();
+
+        Digest(root)
+    }
+
+    /// The sibling path needed to prove that the item at `index` is included
+    /// under [`MerkleDigest::root`].
+    pub fn proof(&self, index: usize) -> MerkleProof<A> {
+        let mut siblings = Vec::new();
+        let mut idx = index;
+
+        for level in &self.levels[..self.levels.len() - 1] {
+            let is_right_child = idx % 2 == 1;
+            let sibling_idx = if is_right_child { idx - 1 } else { idx + 1 };
+
+            if let Some(sibling) = level.get(sibling_idx) {
+                siblings.push((sibling.clone(), is_right_child));
+            }
+
+            idx /= 2;
+        }
+
+        MerkleProof { siblings }
+    }
+
+    /// Recompute the root from `leaf` and `proof` and check it matches
+    /// `root`, without needing the rest of the tree.
+    pub fn verify_proof<'a, T, I>(
+        root: &Digest<A>,
+        leaf: &'a T,
+        proof: &MerkleProof<A>,
+    ) -> bool
+    where
+        T: Digestable<'a, I>,
+        I: Iterator<Item = Cow<'a, [u8]>>,
+    {
+        let mut hash = Self::leaf_hash(leaf);
+
+        for (sibling, sibling_is_left) in &proof.siblings {
+            hash = if *sibling_is_left {
+                Self::node_hash(sibling, &hash)
+            } else {
+                Self::node_hash(&hash, sibling)
+            };
+        }
+
+        hash == root.0
+    }
+
+    fn leaf_hash<'a, T, I>(item: &'a T) -> MerkleHash<A>
+    where
+        T: Digestable<'a, I>,
+        I: Iterator<Item = Cow<'a, [u8]>>,
+    {
+        let mut hasher = A::new();
+        hasher.update(MERKLE_LEAF_PREFIX);
+        for chunk in item.digestable() {
+            hasher.update(chunk);
+        }
+        hasher.finalize()
+    }
+
+    fn node_hash(left: &MerkleHash<A>, right: &MerkleHash<A>) -> MerkleHash<A> {
+        let mut hasher = A::new();
+        hasher.update(MERKLE_NODE_PREFIX);
+        hasher.update(left);
+        hasher.update(right);
+        hasher.finalize()
+    }
+}
+
+pub trait Digestable<'a, I: Iterator<Item = Cow<'a, [u8]>>> {
     /// Get an iterator over a data structure returning each
     /// field in sequence as a byte slice
     fn digestable(&'a self) -> I;
@@ -47,59 +407,181 @@ pub trait Digestable2<'a> {
     fn digestable(&'a self) -> &'a [u8];
 }
 
-impl<'a> Digestable<'a, std::iter::Once<&'a [u8]>> for String {
-    fn digestable(&'a self) -> std::iter::Once<&'a [u8]> {
-        std::iter::once(self.as_bytes())
+impl<'a> Digestable<'a, std::iter::Once<Cow<'a, [u8]>>> for String {
+    fn digestable(&'a self) -> std::iter::Once<Cow<'a, [u8]>> {
+        std::iter::once(Cow::Borrowed(self.as_bytes()))
+    }
+}
+
+impl<'a> Digestable<'a, std::iter::Once<Cow<'a, [u8]>>> for f64 {
+    fn digestable(&'a self) -> std::iter::Once<Cow<'a, [u8]>> {
+        std::iter::once(Cow::Owned(self.to_be_bytes().to_vec()))
+    }
+}
+
+impl<'a, A, B, IA, IB> Digestable<'a, std::iter::Chain<IA, IB>> for (A, B)
+where
+    A: Digestable<'a, IA>,
+    B: Digestable<'a, IB>,
+    IA: Iterator<Item = Cow<'a, [u8]>>,
+    IB: Iterator<Item = Cow<'a, [u8]>>,
+{
+    fn digestable(&'a self) -> std::iter::Chain<IA, IB> {
+        self.0.digestable().chain(self.1.digestable())
     }
 }
 
-struct OwnedOnce<'a> {
-    slice: [u8; 8],
-    slice_ref: Option<&'a [u8]>,
+/// An iterator over a single, owned chunk of bytes computed up front (a
+/// whole length-prefixed sequence or map). `I` carries no data; it's a
+/// witness tying this type to the element `Digestable` impl that produced
+/// `bytes`, so the surrounding `Digestable<'a, OwnedBytes<'a, I>>` impls
+/// don't leave `I` unconstrained.
+///
+/// Yielding `Cow::Owned` rather than a borrowed `&'a [u8]` means `bytes`
+/// doesn't need to outlive `'a` itself, so there's no self-referential
+/// struct to get wrong and nothing to leak.
+///
+/// Public (but with private fields) because it's the `I` in `impl
+/// Digestable<'a, OwnedBytes<'a, I>>` for `[T]`/`Vec<T>`/the map types:
+/// callers outside this crate never name it directly (it's inferred), but
+/// it still has to be reachable from those public impls or the compiler
+/// rejects the call.
+pub struct OwnedBytes<'a, I> {
+    bytes: Option<Vec<u8>>,
+    _iter: std::marker::PhantomData<&'a I>,
+}
+
+impl<'a, I> OwnedBytes<'a, I> {
+    fn new(bytes: Vec<u8>) -> Self {
+        OwnedBytes { bytes: Some(bytes), _iter: std::marker::PhantomData }
+    }
 }
 
-impl<'a> Iterator for OwnedOnce<'a> {
-    type Item = &'a [u8];
+impl<'a, I> Iterator for OwnedBytes<'a, I> {
+    type Item = Cow<'a, [u8]>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.slice_ref = Some(&self.slice);
+        self.bytes.take().map(Cow::Owned)
+    }
+}
+
+/// Flatten a `Digestable` value into a single owned byte buffer, preserving
+/// its own internal chunk boundaries: each chunk its `digestable()`
+/// iterator yields gets its own 8-byte big-endian length prefix, and the
+/// chunk count is prefixed ahead of all of them. Without this, a composite
+/// value's *flattened* bytes would be ambiguous in exactly the way this
+/// request exists to fix — e.g. the tuple `("ab", "c")` and `("a", "bc")`
+/// both flatten to `"abc"`.
+fn length_prefixed_bytes<'a, T, I>(val: &'a T) -> Vec<u8>
+where
+    T: Digestable<'a, I>,
+    I: Iterator<Item = Cow<'a, [u8]>>,
+{
+    let chunks: Vec<Cow<'a, [u8]>> = val.digestable().collect();
 
-        self.slice_ref
+    let mut bytes = (chunks.len() as u64).to_be_bytes().to_vec();
+    for chunk in chunks {
+        bytes.extend_from_slice(&(chunk.len() as u64).to_be_bytes());
+        bytes.extend_from_slice(&chunk);
     }
+    bytes
 }
 
-impl<'a> Digestable<'a, OwnedOnce<'a>> for f64 {
-    fn digestable(&'a self) -> OwnedOnce<'a> {
-        let bytes = self.to_be_bytes();
-        let iter = OwnedOnce { slice: bytes, slice_ref: None };
+/// Digest a sequence of length-prefixed elements, so that e.g. `("ab", "c")`
+/// and `("a", "bc")`, or `vec!["foo"]` and `"foo"`, can't collide: the
+/// element count comes first, then each element is preceded by its own
+/// byte length as a fixed 8-byte big-endian prefix.
+fn digest_sequence_bytes<'a, T, I>(items: impl Iterator<Item = &'a T>, len: usize) -> Vec<u8>
+where
+    T: Digestable<'a, I> + 'a,
+    I: Iterator<Item = Cow<'a, [u8]>>,
+{
+    let mut bytes = (len as u64).to_be_bytes().to_vec();
+    for item in items {
+        let item_bytes = length_prefixed_bytes(item);
+        bytes.extend_from_slice(&(item_bytes.len() as u64).to_be_bytes());
+        bytes.extend_from_slice(&item_bytes);
+    }
+    bytes
+}
 
-        iter
+/// Digest a map's entries, sorted by key bytes so the result doesn't depend
+/// on iteration order (important for `HashMap`, whose order isn't stable).
+fn digest_map_bytes<'a, K, V, IK, IV>(
+    entries: impl Iterator<Item = (&'a K, &'a V)>,
+    len: usize,
+) -> Vec<u8>
+where
+    K: Digestable<'a, IK> + 'a,
+    V: Digestable<'a, IV> + 'a,
+    IK: Iterator<Item = Cow<'a, [u8]>>,
+    IV: Iterator<Item = Cow<'a, [u8]>>,
+{
+    let mut pairs: Vec<(Vec<u8>, Vec<u8>)> = entries
+        .map(|(k, v)| (length_prefixed_bytes(k), length_prefixed_bytes(v)))
+        .collect();
+    pairs.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut bytes = (len as u64).to_be_bytes().to_vec();
+    for (key_bytes, value_bytes) in pairs {
+        bytes.extend_from_slice(&(key_bytes.len() as u64).to_be_bytes());
+        bytes.extend_from_slice(&key_bytes);
+        bytes.extend_from_slice(&(value_bytes.len() as u64).to_be_bytes());
+        bytes.extend_from_slice(&value_bytes);
     }
+    bytes
 }
 
+impl<'a, T, I> Digestable<'a, OwnedBytes<'a, I>> for [T]
+where
+    T: Digestable<'a, I> + 'a,
+    I: Iterator<Item = Cow<'a, [u8]>>,
+{
+    fn digestable(&'a self) -> OwnedBytes<'a, I> {
+        let bytes = digest_sequence_bytes(self.iter(), self.len());
 
-impl<'a, A, B, IA, IB> Digestable<'a, std::iter::Chain<IA, IB>> for (A, B)
+        OwnedBytes::new(bytes)
+    }
+}
+
+impl<'a, T, I> Digestable<'a, OwnedBytes<'a, I>> for Vec<T>
 where
-    A: Digestable<'a, IA>,
-    B: Digestable<'a, IB>,
-    IA: Iterator<Item = &'a [u8]>,
-    IB: Iterator<Item = &'a [u8]>,
+    T: Digestable<'a, I> + 'a,
+    I: Iterator<Item = Cow<'a, [u8]>>,
 {
-    fn digestable(&'a self) -> std::iter::Chain<IA, IB> {
-        self.0.digestable().chain(self.1.digestable())
+    fn digestable(&'a self) -> OwnedBytes<'a, I> {
+        self.as_slice().digestable()
+    }
+}
+
+impl<'a, K, V, IK, IV> Digestable<'a, OwnedBytes<'a, (IK, IV)>> for BTreeMap<K, V>
+where
+    K: Digestable<'a, IK> + 'a,
+    V: Digestable<'a, IV> + 'a,
+    IK: Iterator<Item = Cow<'a, [u8]>>,
+    IV: Iterator<Item = Cow<'a, [u8]>>,
+{
+    fn digestable(&'a self) -> OwnedBytes<'a, (IK, IV)> {
+        let bytes = digest_map_bytes(self.iter(), self.len());
+
+        OwnedBytes::new(bytes)
     }
 }
 
-/*
-impl<'a, A> Digestable<'a, std::iter::Once<&'a [u8]>> for Vec<A>
+impl<'a, K, V, IK, IV> Digestable<'a, OwnedBytes<'a, (IK, IV)>> for HashMap<K, V>
 where
-    A: Digestable<'a, I>,
+    K: Digestable<'a, IK> + 'a,
+    V: Digestable<'a, IV> + 'a,
+    IK: Iterator<Item = Cow<'a, [u8]>>,
+    IV: Iterator<Item = Cow<'a, [u8]>>,
 {
-    fn digestable(&self) -> std::iter::Once<A> {
-        self.iter().map(Digestable::digestable)
+    fn digestable(&'a self) -> OwnedBytes<'a, (IK, IV)> {
+        let bytes = digest_map_bytes(self.iter(), self.len());
+
+        OwnedBytes::new(bytes)
     }
 }
-*/
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -125,7 +607,7 @@ mod tests {
         let some_string = "foobar".to_string();
 
         for s in some_string.digestable() {
-            assert_eq!(s, some_string.as_bytes());
+            assert_eq!(s.as_ref(), some_string.as_bytes());
         }
     }
 
@@ -151,10 +633,206 @@ mod tests {
         DigestSha256::from_digestable(&some_tuple);
     }
 
+    #[test]
+    fn test_digest_display_round_trip() {
+        let sha256 = "sha256:e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+        let digest = DigestSha256::from_str(sha256).expect("failed to decode");
+
+        assert_eq!(digest.to_string(), sha256);
+    }
+
+    #[test]
+    fn test_digest_from_str_bare_hex_defaults_to_algorithm() {
+        let sha256 = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+        DigestSha256::from_str(sha256).expect("failed to decode");
+    }
+
+    #[test]
+    fn test_digest_from_str_unknown_algorithm() {
+        let sha512_tagged = "sha512:e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+        let err = DigestSha256::from_str(sha512_tagged).expect_err("should reject mismatched algorithm");
+
+        assert!(matches!(err, ParseDigestError::UnknownAlgorithm(_)));
+    }
+
+    #[test]
+    fn test_digest_lower_hex_has_no_algorithm_prefix() {
+        let sha256 = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+        let digest = DigestSha256::from_str(sha256).expect("failed to decode");
+
+        assert_eq!(format!("{:x}", digest), sha256);
+    }
+
+    #[test]
+    fn test_digest_equality_and_ordering() {
+        let empty = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+        let other = "0000000000000000000000000000000000000000000000000000000000000000";
+
+        let empty_digest = DigestSha256::from_str(empty).expect("failed to decode");
+        let empty_digest_again = DigestSha256::from_str(empty).expect("failed to decode");
+        let other_digest = DigestSha256::from_str(other).expect("failed to decode");
+
+        assert_eq!(empty_digest, empty_digest_again);
+        assert!(other_digest < empty_digest);
+    }
+
+    #[test]
+    fn test_digest_verify_matching() {
+        let some_string = "foobar".to_string();
+        let digest = DigestSha256::from_digestable(&some_string);
+
+        assert!(digest.verify(&some_string));
+    }
+
+    #[test]
+    fn test_digest_verify_mismatch() {
+        let some_string = "foobar".to_string();
+        let other_string = "barfoo".to_string();
+        let digest = DigestSha256::from_digestable(&some_string);
+
+        assert!(!digest.verify(&other_string));
+    }
+
     #[test]
     fn test_digest_from_vec() {
         let some_vec = vec!["foo".to_string(), "bar".to_string()];
 
         DigestSha256::from_digestable(&some_vec);
     }
+
+    #[test]
+    fn test_vec_of_one_string_does_not_collide_with_the_string() {
+        let wrapped = vec!["foo".to_string()];
+        let bare = "foo".to_string();
+
+        let wrapped_digest = DigestSha256::from_digestable(&wrapped);
+        let bare_digest = DigestSha256::from_digestable(&bare);
+
+        assert_ne!(wrapped_digest, bare_digest);
+    }
+
+    #[test]
+    fn test_vec_element_boundaries_are_unambiguous() {
+        let first = vec!["ab".to_string(), "c".to_string()];
+        let second = vec!["a".to_string(), "bc".to_string()];
+
+        let first_digest = DigestSha256::from_digestable(&first);
+        let second_digest = DigestSha256::from_digestable(&second);
+
+        assert_ne!(first_digest, second_digest);
+    }
+
+    #[test]
+    fn test_vec_of_tuples_element_boundaries_are_unambiguous() {
+        let first = vec![("ab".to_string(), "c".to_string())];
+        let second = vec![("a".to_string(), "bc".to_string())];
+
+        let first_digest = DigestSha256::from_digestable(&first);
+        let second_digest = DigestSha256::from_digestable(&second);
+
+        assert_ne!(first_digest, second_digest);
+    }
+
+    #[test]
+    fn test_http_digest_header_round_trip() {
+        let sha256 = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+        let digest = DigestSha256::from_str(sha256).expect("failed to decode");
+
+        let header = digest.to_http_header();
+        assert!(header.starts_with("SHA-256="));
+
+        let round_tripped = DigestSha256::from_http_header(&header).expect("failed to decode");
+        assert_eq!(digest, round_tripped);
+    }
+
+    #[test]
+    fn test_http_digest_header_case_folds_algorithm() {
+        let sha256 = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+        let digest = DigestSha256::from_str(sha256).expect("failed to decode");
+
+        // Lowercase only the algorithm token; base64 is case-sensitive.
+        let header = digest.to_http_header().replace("SHA-256", "sha-256");
+
+        DigestSha256::from_http_header(&header).expect("failed to decode lowercase header");
+    }
+
+    #[test]
+    fn test_http_digest_header_rejects_wrong_algorithm() {
+        let header = "SHA-512=AAAA";
+
+        let err = DigestSha256::from_http_header(header).expect_err("should reject mismatched algorithm");
+
+        assert!(matches!(err, ParseHttpDigestError::UnknownAlgorithm(_)));
+    }
+
+    #[test]
+    fn test_merkle_proof_verifies_for_every_leaf_even_count() {
+        let items = vec![
+            "foo".to_string(),
+            "bar".to_string(),
+            "baz".to_string(),
+            "qux".to_string(),
+        ];
+        let tree = MerkleDigest::<Sha256>::new(&items).expect("items is non-empty");
+        let root = tree.root();
+
+        for (index, item) in items.iter().enumerate() {
+            let proof = tree.proof(index);
+            assert!(MerkleDigest::<Sha256>::verify_proof(&root, item, &proof));
+        }
+    }
+
+    #[test]
+    fn test_merkle_proof_verifies_with_odd_leaf_count() {
+        let items = vec!["foo".to_string(), "bar".to_string(), "baz".to_string()];
+        let tree = MerkleDigest::<Sha256>::new(&items).expect("items is non-empty");
+        let root = tree.root();
+
+        for (index, item) in items.iter().enumerate() {
+            let proof = tree.proof(index);
+            assert!(MerkleDigest::<Sha256>::verify_proof(&root, item, &proof));
+        }
+    }
+
+    #[test]
+    fn test_merkle_proof_rejects_wrong_leaf() {
+        let items = vec!["foo".to_string(), "bar".to_string()];
+        let tree = MerkleDigest::<Sha256>::new(&items).expect("items is non-empty");
+        let root = tree.root();
+        let proof = tree.proof(0);
+
+        let wrong_leaf = "not foo".to_string();
+        assert!(!MerkleDigest::<Sha256>::verify_proof(
+            &root,
+            &wrong_leaf,
+            &proof
+        ));
+    }
+
+    #[test]
+    fn test_merkle_digest_of_empty_items_is_none() {
+        let items: Vec<String> = Vec::new();
+
+        assert!(MerkleDigest::<Sha256>::new(&items).is_none());
+    }
+
+    #[test]
+    fn test_hashmap_digest_is_order_independent() {
+        let mut first = HashMap::new();
+        first.insert("a".to_string(), "1".to_string());
+        first.insert("b".to_string(), "2".to_string());
+
+        let mut second = HashMap::new();
+        second.insert("b".to_string(), "2".to_string());
+        second.insert("a".to_string(), "1".to_string());
+
+        let first_digest = DigestSha256::from_digestable(&first);
+        let second_digest = DigestSha256::from_digestable(&second);
+
+        assert_eq!(first_digest, second_digest);
+    }
 }